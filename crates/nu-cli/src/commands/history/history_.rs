@@ -1,11 +1,11 @@
+use chrono::{DateTime, FixedOffset, Utc};
 use nu_engine::command_prelude::*;
 use nu_protocol::HistoryFileFormat;
 use reedline::{
-    FileBackedHistory, History as ReedlineHistory, HistoryItem, SearchDirection, SearchQuery,
-    SqliteBackedHistory, RqliteBackedHistory, HistoryStorageDest,
-    ReedlineError, ReedlineErrorVariants,
+    HistoryItem, HistorySessionId, SearchDirection, SearchFilter, SearchQuery,
 };
-use nu_protocol::{HISTORY_DEST_TXT, HISTORY_DEST_SQLITE};
+
+use super::store::{open_store, HistoryStore};
 
 #[derive(Clone)]
 pub struct History;
@@ -29,6 +29,40 @@ impl Command for History {
                 "Show long listing of entries for sqlite history",
                 Some('l'),
             )
+            .named(
+                "cwd",
+                SyntaxShape::Directory,
+                "Only list entries recorded in the given directory (pass $env.PWD for the current one)",
+                Some('d'),
+            )
+            .switch(
+                "session",
+                "Only list entries recorded in the current session",
+                Some('s'),
+            )
+            .named(
+                "exit",
+                SyntaxShape::Int,
+                "Only list entries whose command exited with the given status",
+                Some('e'),
+            )
+            .named(
+                "after",
+                SyntaxShape::DateTime,
+                "Only list entries recorded at or after the given timestamp",
+                None,
+            )
+            .named(
+                "before",
+                SyntaxShape::DateTime,
+                "Only list entries recorded at or before the given timestamp",
+                None,
+            )
+            .switch(
+                "unique",
+                "Collapse duplicate command lines, keeping the most recent",
+                Some('u'),
+            )
             .category(Category::History)
     }
 
@@ -51,106 +85,109 @@ impl Command for History {
             Some(config_path) => {
                 let clear = call.has_flag(engine_state, stack, "clear")?;
                 let long = call.has_flag(engine_state, stack, "long")?;
+                let unique = call.has_flag(engine_state, stack, "unique")?;
                 let ctrlc = engine_state.ctrlc.clone();
 
-                let history_dest = match history.file_format {
-                    | HistoryFileFormat::Sqlite
-                    | HistoryFileFormat::PlainText
-                    => {
-                        let mut history_path = config_path;
-                        history_path.push("nushell");
-                        if matches!(history.file_format, HistoryFileFormat::Sqlite)
-                        {
-                            history_path.push(HISTORY_DEST_SQLITE);
-                        } else {
-                            history_path.push(HISTORY_DEST_TXT);
-                        }
+                let cwd = call.get_flag::<String>(engine_state, stack, "cwd")?;
+                let session = call
+                    .has_flag(engine_state, stack, "session")?
+                    .then(|| HistorySessionId::new(engine_state.history_session_id));
+                let exit = call.get_flag::<i64>(engine_state, stack, "exit")?;
+                let after = call
+                    .get_flag::<DateTime<FixedOffset>>(engine_state, stack, "after")?
+                    .map(|ts| ts.with_timezone(&Utc));
+                let before = call
+                    .get_flag::<DateTime<FixedOffset>>(engine_state, stack, "before")?
+                    .map(|ts| ts.with_timezone(&Utc));
+
+                // The plaintext (FileBackedHistory) backend stores only command
+                // lines, so it can't express any of the structured filters and its
+                // records keep their historical `{command, index}` shape. Reject
+                // the filtering flags here rather than silently ignoring them.
+                if matches!(history.file_format, HistoryFileFormat::PlainText) {
+                    if let Some(flag) = first_unsupported_flag(
+                        cwd.is_some(),
+                        session.is_some(),
+                        exit.is_some(),
+                        after.is_some(),
+                        before.is_some(),
+                        unique,
+                    ) {
+                        return Err(ShellError::GenericError {
+                            error: format!(
+                                "`--{flag}` is not supported for the plaintext history backend"
+                            ),
+                            msg: "the plaintext backend stores only command lines".into(),
+                            span: Some(head),
+                            help: Some(
+                                "set `$env.config.history.file_format = \"sqlite\"` to filter history"
+                                    .into(),
+                            ),
+                            inner: vec![],
+                        });
+                    }
 
-                        HistoryStorageDest::Path(history_path)
+                    let store = open_store(&history, config_path, head)?;
+                    if clear {
+                        store.clear()?;
+                        return Ok(PipelineData::empty());
                     }
-                    HistoryFileFormat::Rqlite => history.rqlite_url.into(),
+
+                    let entries =
+                        store.search(SearchQuery::everything(SearchDirection::Forward, None))?;
+                    return Ok(entries
+                        .into_iter()
+                        .enumerate()
+                        .map(move |(idx, entry)| {
+                            Value::record(
+                                record! {
+                                    "command" => Value::string(entry.command_line, head),
+                                    "index" => Value::int(idx as i64, head),
+                                },
+                                head,
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .into_pipeline_data(head, ctrlc));
+                }
+
+                // Push everything reedline can express down into the query; the
+                // exit status and uniqueness are applied afterwards on the stream.
+                let query = SearchQuery {
+                    direction: SearchDirection::Forward,
+                    start_time: after,
+                    end_time: before,
+                    start_id: None,
+                    end_id: None,
+                    limit: None,
+                    filter: SearchFilter {
+                        command_line: None,
+                        not_command_line: None,
+                        hostname: None,
+                        cwd_exact: cwd,
+                        cwd_prefix: None,
+                        exit_successful: None,
+                        session,
+                    },
                 };
 
+                // The SQLite and Rqlite backends share one "search and map to
+                // records" path through the store interface; only the SQLite
+                // handle is pooled and reused across calls.
+                let store = open_store(&history, config_path, head)?;
+
                 if clear {
-                    if let HistoryStorageDest::Path(history_path) = history_dest {
-                        let _ = std::fs::remove_file(history_path);
-                        // TODO: FIXME also clear the auxiliary files when using sqlite
-                    }
+                    store.clear()?;
                     return Ok(PipelineData::empty());
                 }
-                let history_reader: Box<dyn ReedlineHistory> = match history.file_format {
-                    HistoryFileFormat::Sqlite => SqliteBackedHistory::with_file(history_dest.clone(), None, None)
-                        .map(|inner| {
-                            let boxed: Box<dyn ReedlineHistory> = Box::new(inner);
-                            boxed
-                        })
-                        .map_err(map_shell_io_error(history_dest.clone())),
-                    HistoryFileFormat::PlainText => FileBackedHistory::with_file(history.max_size as usize, history_dest.clone())
-                        .map(|inner| {
-                            let boxed: Box<dyn ReedlineHistory> = Box::new(inner);
-                            boxed
-                        })
-                        .map_err(map_shell_io_error(history_dest.clone())),
-                    HistoryFileFormat::Rqlite => RqliteBackedHistory::with_url(history_dest.clone(), None, None)
-                        .map(|inner| {
-                            let boxed: Box<dyn ReedlineHistory> = Box::new(inner);
-                            boxed
-                        })
-                        .map_err(|err| ShellError::NetworkFailure {
-                            msg: format!("Failed to connect rqlite: {history_dest}\n{err:?}"),
-                            span: head,
-                        }),
-                }?;
 
-                match history.file_format {
-                    HistoryFileFormat::PlainText => Ok(history_reader
-                        .search(SearchQuery::everything(SearchDirection::Forward, None))
-                        .map(move |entries| {
-                            entries.into_iter().enumerate().map(move |(idx, entry)| {
-                                Value::record(
-                                    record! {
-                                        "command" => Value::string(entry.command_line, head),
-                                        "index" => Value::int(idx as i64, head),
-                                    },
-                                    head,
-                                )
-                            })
-                        })
-                        .map_err(|_| ShellError::FileNotFound {
-                            file: history_dest.to_string(),
-                            span: head,
-                        })?
-                        .into_pipeline_data(head, ctrlc)),
-                    HistoryFileFormat::Sqlite => Ok(history_reader
-                        .search(SearchQuery::everything(SearchDirection::Forward, None))
-                        .map(move |entries| {
-                            entries.into_iter().enumerate().map(move |(idx, entry)| {
-                                create_history_record(idx, entry, long, head)
-                            })
-                        })
-                        .map_err(|_| ShellError::FileNotFound {
-                            file: history_dest.to_string(),
-                            span: head,
-                        })?
-                        .into_pipeline_data(head, ctrlc)),
-                    HistoryFileFormat::Rqlite => Ok(history_reader
-                        .search(SearchQuery::everything(SearchDirection::Forward, None))
-                        .map(move |entries|
-                            entries.into_iter().enumerate().map(move |(idx, entry)|
-                                create_history_record(idx, entry, long, head)
-                            )
-                        )
-                        .map_err(|err| ShellError::NetworkFailure {
-                            msg: if let ReedlineError(ReedlineErrorVariants::HistoryDatabaseError(msg)) = err {
-                                format!("Failed to connect rqlite: {history_dest}\n{msg}")
-                            } else {
-                                format!("Failed to connect rqlite: {history_dest}")
-                            },
-                            span: head,
-                        })?
-                        .into_pipeline_data(head, ctrlc)
-                    ),
-                }
+                let entries = refine_entries(store.search(query)?, exit, unique);
+                Ok(entries
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(idx, entry)| create_history_record(idx, entry, long, head))
+                    .collect::<Vec<_>>()
+                    .into_pipeline_data(head, ctrlc))
             }
         }
     }
@@ -172,16 +209,68 @@ impl Command for History {
                 description: "Search all the commands from history that contains 'cargo'",
                 result: None,
             },
+            Example {
+                example: "history --session --unique",
+                description: "Show the unique commands run in the current session",
+                result: None,
+            },
+            Example {
+                example: "history --cwd $env.PWD",
+                description: "Show commands run in the current directory",
+                result: None,
+            },
+            Example {
+                example: "history --exit 0 --after 2024-01-01",
+                description: "Show successful commands run on or after a given date",
+                result: None,
+            },
         ]
     }
 }
 
-fn map_shell_io_error(dest: HistoryStorageDest) -> impl Fn(ReedlineError) -> ShellError {
-    move |err| {
-        ShellError::IOError {
-            msg: format!("{}, {:?}", dest, err),
-        }
+/// Name the first structured filter that was requested, so the plaintext backend
+/// can reject it by name instead of ignoring it. Order matches the signature.
+fn first_unsupported_flag(
+    cwd: bool,
+    session: bool,
+    exit: bool,
+    after: bool,
+    before: bool,
+    unique: bool,
+) -> Option<&'static str> {
+    [
+        (cwd, "cwd"),
+        (session, "session"),
+        (exit, "exit"),
+        (after, "after"),
+        (before, "before"),
+        (unique, "unique"),
+    ]
+    .into_iter()
+    .find_map(|(set, name)| set.then_some(name))
+}
+
+/// Apply the filters reedline's `SearchQuery` cannot express directly: an exact
+/// exit status and, optionally, collapsing duplicate command lines to the most
+/// recent occurrence. Entries are assumed to be in forward (oldest-first) order.
+fn refine_entries(entries: Vec<HistoryItem>, exit: Option<i64>, unique: bool) -> Vec<HistoryItem> {
+    let entries = entries
+        .into_iter()
+        .filter(|entry| exit.map_or(true, |code| entry.exit_status == Some(code)));
+
+    if !unique {
+        return entries.collect();
     }
+
+    // Walk newest-first so the first command line we see is the one we keep,
+    // then restore the original oldest-first order for display.
+    let mut seen = std::collections::HashSet::new();
+    let mut kept: Vec<HistoryItem> = entries
+        .rev()
+        .filter(|entry| seen.insert(entry.command_line.clone()))
+        .collect();
+    kept.reverse();
+    kept
 }
 
 fn create_history_record(idx: usize, entry: HistoryItem, long: bool, head: Span) -> Value {
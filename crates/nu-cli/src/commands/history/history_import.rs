@@ -0,0 +1,363 @@
+use chrono::{DateTime, TimeZone, Utc};
+use nu_engine::command_prelude::*;
+use nu_protocol::HistoryFileFormat;
+use reedline::{HistoryItem, SearchDirection, SearchQuery};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::store::{open_store, HistoryStore};
+
+/// History file dialects we know how to read.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ImportFormat {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+#[derive(Clone)]
+pub struct HistoryImport;
+
+impl Command for HistoryImport {
+    fn name(&self) -> &str {
+        "history import"
+    }
+
+    fn usage(&self) -> &str {
+        "Import command history from another shell into Nushell's history backend."
+    }
+
+    fn extra_usage(&self) -> &str {
+        "Reads bash, zsh (extended) or fish history and writes the entries into \
+whichever backend `history_config()` selects. Entries with the same timestamp and \
+command line as one already stored are skipped (the command line alone for the \
+plaintext backend, which stores no timestamps), so importing the same file twice \
+is a no-op while genuine re-runs of a command are kept."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        Signature::build("history import")
+            .input_output_types(vec![(Type::Nothing, Type::Record(vec![].into()))])
+            .optional(
+                "file",
+                SyntaxShape::Filepath,
+                "History file to read (defaults to $HISTFILE)",
+            )
+            .named(
+                "shell",
+                SyntaxShape::String,
+                "Source shell format: 'bash', 'zsh' or 'fish' (auto-detected otherwise)",
+                Some('s'),
+            )
+            .category(Category::History)
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let head = call.head;
+
+        let Some(history) = engine_state.history_config() else {
+            return Ok(PipelineData::empty());
+        };
+
+        // Resolve the source file: explicit argument wins, otherwise $HISTFILE.
+        // `$HISTFILE` is looked up in Nushell's environment (`stack`/`engine_state`)
+        // first, then in the OS process env.
+        let path = match call.opt::<String>(engine_state, stack, 0)? {
+            Some(path) => path,
+            None => histfile_from_env(engine_state, stack).ok_or_else(|| {
+                ShellError::GenericError {
+                    error: "no history file to import".into(),
+                    msg: "pass a path or set $env.HISTFILE".into(),
+                    span: Some(head),
+                    help: None,
+                    inner: vec![],
+                }
+            })?,
+        };
+
+        let contents = std::fs::read_to_string(&path).map_err(|err| ShellError::IOError {
+            msg: format!("{path}: {err}"),
+        })?;
+
+        let format = match call.get_flag::<String>(engine_state, stack, "shell")? {
+            Some(shell) => match shell.as_str() {
+                "bash" => ImportFormat::Bash,
+                "zsh" => ImportFormat::Zsh,
+                "fish" => ImportFormat::Fish,
+                other => {
+                    return Err(ShellError::IncorrectValue {
+                        msg: format!("unknown shell '{other}', expected bash, zsh or fish"),
+                        val_span: head,
+                        call_span: head,
+                    })
+                }
+            },
+            None => detect_format(&path, &contents),
+        };
+
+        let items = parse_history(&contents, format);
+
+        // Open the configured backend for writing.
+        let config_path = nu_path::config_dir().ok_or(ShellError::ConfigDirNotFound {
+            span: Some(head),
+        })?;
+        let store = open_store(&history, config_path, head)?;
+
+        // Count how many entries the store already holds per content key. We
+        // skip an imported item only while the store still has an unmatched copy
+        // of that key, so re-importing the same file is a no-op but genuine
+        // reruns of a command — which share a content key when they carry no
+        // timestamp, as plain bash history does — are all kept. A backend error
+        // is propagated rather than swallowed; otherwise we'd silently re-import
+        // everything as new.
+        // The plaintext backend stores only command lines, so its entries come
+        // back without timestamps; compare on the command line alone there.
+        let with_ts = !matches!(history.file_format, HistoryFileFormat::PlainText);
+        let existing = store.search(SearchQuery::everything(SearchDirection::Forward, None))?;
+        let mut remaining: HashMap<String, usize> = HashMap::new();
+        for entry in &existing {
+            *remaining.entry(dedup_key(entry, with_ts)).or_default() += 1;
+        }
+
+        let mut imported = 0usize;
+        let mut skipped = 0usize;
+        for item in items {
+            match remaining.get_mut(&dedup_key(&item, with_ts)) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    skipped += 1;
+                }
+                _ => {
+                    store.save(item)?;
+                    imported += 1;
+                }
+            }
+        }
+        store.sync()?;
+
+        Ok(Value::record(
+            record! {
+                "imported" => Value::int(imported as i64, head),
+                "skipped" => Value::int(skipped as i64, head),
+                "source" => Value::string(path, head),
+            },
+            head,
+        )
+        .into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                example: "history import",
+                description: "Import history from the file named in $HISTFILE",
+                result: None,
+            },
+            Example {
+                example: "history import ~/.zsh_history --shell zsh",
+                description: "Import a zsh history file",
+                result: None,
+            },
+        ]
+    }
+}
+
+/// Resolve `$HISTFILE` from Nushell's environment, falling back to the OS
+/// process environment for users who only export it there.
+fn histfile_from_env(engine_state: &EngineState, stack: &Stack) -> Option<String> {
+    stack
+        .get_env_var(engine_state, "HISTFILE")
+        .and_then(|value| value.coerce_string().ok())
+        .filter(|path| !path.is_empty())
+        .or_else(|| std::env::var("HISTFILE").ok().filter(|path| !path.is_empty()))
+}
+
+/// Guess the source dialect from the file name and its first meaningful line.
+fn detect_format(path: &str, contents: &str) -> ImportFormat {
+    if path.contains("fish") {
+        return ImportFormat::Fish;
+    }
+    match contents.lines().find(|line| !line.trim().is_empty()) {
+        Some(line) if line.starts_with("- cmd:") => ImportFormat::Fish,
+        Some(line) if line.starts_with(": ") && line.contains(';') => ImportFormat::Zsh,
+        _ => ImportFormat::Bash,
+    }
+}
+
+fn parse_history(contents: &str, format: ImportFormat) -> Vec<HistoryItem> {
+    match format {
+        ImportFormat::Bash => parse_bash(contents),
+        ImportFormat::Zsh => parse_zsh(contents),
+        ImportFormat::Fish => parse_fish(contents),
+    }
+}
+
+/// Bash writes one command per line. With `HISTTIMEFORMAT` set it prefixes each
+/// command with a `#<epoch>` comment line, which we fold into the next command.
+fn parse_bash(contents: &str) -> Vec<HistoryItem> {
+    let mut items = Vec::new();
+    let mut pending_ts = None;
+    for line in contents.lines() {
+        if let Some(epoch) = line.strip_prefix('#') {
+            if let Ok(secs) = epoch.trim().parse::<i64>() {
+                pending_ts = timestamp_from_secs(secs);
+                continue;
+            }
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let mut item = HistoryItem::from_command_line(line);
+        item.start_timestamp = pending_ts.take();
+        items.push(item);
+    }
+    items
+}
+
+/// Zsh's extended format is `: <start>:<duration>;<command>`.
+fn parse_zsh(contents: &str) -> Vec<HistoryItem> {
+    let mut items = Vec::new();
+    for line in contents.lines() {
+        if line.is_empty() {
+            continue;
+        }
+        let (start, duration, command) = match parse_zsh_line(line) {
+            Some(parsed) => parsed,
+            None => (None, None, line.to_string()),
+        };
+        let mut item = HistoryItem::from_command_line(command);
+        item.start_timestamp = start;
+        item.duration = duration;
+        items.push(item);
+    }
+    items
+}
+
+fn parse_zsh_line(line: &str) -> Option<(Option<DateTime<Utc>>, Option<Duration>, String)> {
+    let rest = line.strip_prefix(": ")?;
+    let (meta, command) = rest.split_once(';')?;
+    let (start, dur) = meta.split_once(':')?;
+    let start = start.trim().parse::<i64>().ok().and_then(timestamp_from_secs);
+    let duration = dur.trim().parse::<u64>().ok().map(Duration::from_secs);
+    Some((start, duration, command.to_string()))
+}
+
+/// Fish stores YAML-ish records: a `- cmd:` line optionally followed by a
+/// `when:` timestamp line (and paths we ignore).
+fn parse_fish(contents: &str) -> Vec<HistoryItem> {
+    let mut items: Vec<HistoryItem> = Vec::new();
+    for line in contents.lines() {
+        if let Some(cmd) = line.strip_prefix("- cmd:") {
+            items.push(HistoryItem::from_command_line(unescape_fish(cmd.trim())));
+        } else if let Some(when) = line.trim().strip_prefix("when:") {
+            if let (Some(item), Ok(secs)) = (items.last_mut(), when.trim().parse::<i64>()) {
+                item.start_timestamp = timestamp_from_secs(secs);
+            }
+        }
+    }
+    items
+}
+
+/// Fish escapes embedded newlines and backslashes in command lines. Unescape in
+/// a single left-to-right pass so a literal escaped backslash (`\\`) is consumed
+/// as one token and never re-interpreted as the start of another escape.
+fn unescape_fish(cmd: &str) -> String {
+    let mut out = String::with_capacity(cmd.len());
+    let mut chars = cmd.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('\\') => out.push('\\'),
+                // Unknown escape: keep the backslash and the following char as-is.
+                Some(other) => {
+                    out.push('\\');
+                    out.push(other);
+                }
+                // Trailing backslash with nothing after it.
+                None => out.push('\\'),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn timestamp_from_secs(secs: i64) -> Option<DateTime<Utc>> {
+    Utc.timestamp_opt(secs, 0).single()
+}
+
+/// Content key used for de-duplication: the start timestamp (if any) plus the
+/// command line, so distinct runs of the same command are kept apart. With
+/// `with_ts` false the timestamp is left out of the key.
+fn dedup_key(item: &HistoryItem, with_ts: bool) -> String {
+    let ts = item
+        .start_timestamp
+        .filter(|_| with_ts)
+        .map(|t| t.timestamp())
+        .unwrap_or_default();
+    format!("{ts}\u{0}{}", item.command_line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescape_fish_handles_escapes() {
+        // `\\n` is an escaped backslash followed by a literal `n`.
+        assert_eq!(unescape_fish("\\\\n"), "\\n");
+        // `\\\\` is two escaped backslashes.
+        assert_eq!(unescape_fish("\\\\\\\\"), "\\\\");
+        // `\n` is an escaped newline.
+        assert_eq!(unescape_fish("a\\nb"), "a\nb");
+        // A trailing backslash is preserved verbatim.
+        assert_eq!(unescape_fish("ls\\"), "ls\\");
+    }
+
+    #[test]
+    fn parse_bash_folds_timestamp_comments() {
+        let items = parse_bash("#1700000000\nls -l\necho hi\n");
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].command_line, "ls -l");
+        assert_eq!(items[0].start_timestamp, timestamp_from_secs(1700000000));
+        // The timestamp applies only to the immediately following command.
+        assert_eq!(items[1].command_line, "echo hi");
+        assert_eq!(items[1].start_timestamp, None);
+    }
+
+    #[test]
+    fn parse_zsh_extended_line() {
+        let parsed = parse_zsh_line(": 1700000000:5;git status").unwrap();
+        assert_eq!(parsed.0, timestamp_from_secs(1700000000));
+        assert_eq!(parsed.1, Some(Duration::from_secs(5)));
+        assert_eq!(parsed.2, "git status");
+        // A plain line that isn't in extended format isn't parsed.
+        assert!(parse_zsh_line("git status").is_none());
+    }
+
+    #[test]
+    fn parse_fish_reads_cmd_and_when() {
+        let items = parse_fish("- cmd: echo hi\n  when: 1700000000\n- cmd: ls\n");
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].command_line, "echo hi");
+        assert_eq!(items[0].start_timestamp, timestamp_from_secs(1700000000));
+        assert_eq!(items[1].command_line, "ls");
+        assert_eq!(items[1].start_timestamp, None);
+    }
+
+    #[test]
+    fn detect_format_recognizes_dialects() {
+        assert!(detect_format("~/.local/share/fish/fish_history", "") == ImportFormat::Fish);
+        assert!(detect_format("hist", "- cmd: ls\n") == ImportFormat::Fish);
+        assert!(detect_format("hist", ": 1700000000:0;ls\n") == ImportFormat::Zsh);
+        assert!(detect_format("hist", "ls -l\n") == ImportFormat::Bash);
+    }
+}
@@ -0,0 +1,305 @@
+use chrono::{DateTime, FixedOffset, Local, Timelike, Utc};
+use nu_engine::command_prelude::*;
+use reedline::{HistoryItem, SearchDirection, SearchFilter, SearchQuery};
+use std::collections::HashMap;
+
+use super::store::{open_store, HistoryStore};
+
+const DEFAULT_TOP: usize = 10;
+
+#[derive(Clone)]
+pub struct HistoryStats;
+
+impl Command for HistoryStats {
+    fn name(&self) -> &str {
+        "history stats"
+    }
+
+    fn usage(&self) -> &str {
+        "Compute aggregate statistics over the command history."
+    }
+
+    fn extra_usage(&self) -> &str {
+        "Reports totals, the most frequently run commands, per-day activity, \
+duration averages and the busiest hour of the day, computed over the stored \
+history rather than the current pipeline."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        Signature::build("history stats")
+            .input_output_types(vec![(Type::Nothing, Type::Record(vec![].into()))])
+            .named(
+                "top",
+                SyntaxShape::Int,
+                "How many of the most-run commands to report (default 10)",
+                Some('t'),
+            )
+            .named(
+                "after",
+                SyntaxShape::DateTime,
+                "Only consider entries recorded at or after the given timestamp",
+                None,
+            )
+            .named(
+                "before",
+                SyntaxShape::DateTime,
+                "Only consider entries recorded at or before the given timestamp",
+                None,
+            )
+            .category(Category::History)
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let head = call.head;
+
+        let Some(history) = engine_state.history_config() else {
+            return Ok(PipelineData::empty());
+        };
+
+        let top = call
+            .get_flag::<i64>(engine_state, stack, "top")?
+            .map(|n| n.max(0) as usize)
+            .unwrap_or(DEFAULT_TOP);
+        let after = call
+            .get_flag::<DateTime<FixedOffset>>(engine_state, stack, "after")?
+            .map(|ts| ts.with_timezone(&Utc));
+        let before = call
+            .get_flag::<DateTime<FixedOffset>>(engine_state, stack, "before")?
+            .map(|ts| ts.with_timezone(&Utc));
+
+        let config_path = nu_path::config_dir().ok_or(ShellError::ConfigDirNotFound {
+            span: Some(head),
+        })?;
+        let store = open_store(&history, config_path, head)?;
+
+        let query = SearchQuery {
+            direction: SearchDirection::Forward,
+            start_time: after,
+            end_time: before,
+            start_id: None,
+            end_id: None,
+            limit: None,
+            filter: SearchFilter {
+                command_line: None,
+                not_command_line: None,
+                hostname: None,
+                cwd_exact: None,
+                cwd_prefix: None,
+                exit_successful: None,
+                session: None,
+            },
+        };
+
+        let entries = store.search(query)?;
+
+        Ok(compute_stats(&entries, top, head).into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                example: "history stats",
+                description: "Summarize the whole command history",
+                result: None,
+            },
+            Example {
+                example: "history stats --top 5 --after 2024-01-01",
+                description: "Top 5 commands run on or after a given date",
+                result: None,
+            },
+        ]
+    }
+}
+
+fn compute_stats(entries: &[HistoryItem], top: usize, head: Span) -> Value {
+    let total = entries.len();
+
+    let mut counts: HashMap<&str, i64> = HashMap::new();
+    let mut per_day: HashMap<String, i64> = HashMap::new();
+    let mut per_hour = [0i64; 24];
+    let mut durations: Vec<i64> = Vec::new();
+
+    for entry in entries {
+        *counts.entry(entry.command_line.as_str()).or_default() += 1;
+        if let Some(ts) = entry.start_timestamp {
+            // Bucket by the user's local day/hour, not UTC, so "commands per day"
+            // and "busiest hour" line up with when the commands were actually run.
+            let local = ts.with_timezone(&Local);
+            *per_day
+                .entry(local.format("%Y-%m-%d").to_string())
+                .or_default() += 1;
+            per_hour[local.hour() as usize] += 1;
+        }
+        if let Some(d) = entry.duration {
+            durations.push(d.as_nanos().try_into().unwrap_or(i64::MAX));
+        }
+    }
+
+    let unique = counts.len();
+
+    // Top-N most frequently run commands, ties broken alphabetically.
+    let mut ranked: Vec<(&str, i64)> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    let top_commands: Vec<Value> = ranked
+        .into_iter()
+        .take(top)
+        .map(|(command, count)| {
+            Value::record(
+                record! {
+                    "command" => Value::string(command, head),
+                    "count" => Value::int(count, head),
+                },
+                head,
+            )
+        })
+        .collect();
+
+    // Commands per calendar day, oldest first.
+    let mut per_day: Vec<(String, i64)> = per_day.into_iter().collect();
+    per_day.sort_by(|a, b| a.0.cmp(&b.0));
+    let per_day: Vec<Value> = per_day
+        .into_iter()
+        .map(|(date, count)| {
+            Value::record(
+                record! {
+                    "date" => Value::string(date, head),
+                    "count" => Value::int(count, head),
+                },
+                head,
+            )
+        })
+        .collect();
+
+    let by_hour: Vec<Value> = per_hour
+        .iter()
+        .enumerate()
+        .map(|(hour, count)| {
+            Value::record(
+                record! {
+                    "hour" => Value::int(hour as i64, head),
+                    "count" => Value::int(*count, head),
+                },
+                head,
+            )
+        })
+        .collect();
+    let busiest_hour = per_hour
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, count)| **count)
+        .filter(|(_, count)| **count > 0)
+        .map(|(hour, _)| Value::int(hour as i64, head))
+        .unwrap_or_else(|| Value::nothing(head));
+
+    let (avg_duration, median_duration) = duration_stats(durations, head);
+
+    Value::record(
+        record! {
+            "total" => Value::int(total as i64, head),
+            "unique" => Value::int(unique as i64, head),
+            "top_commands" => Value::list(top_commands, head),
+            "per_day" => Value::list(per_day, head),
+            "by_hour" => Value::list(by_hour, head),
+            "busiest_hour" => busiest_hour,
+            "avg_duration" => avg_duration,
+            "median_duration" => median_duration,
+        },
+        head,
+    )
+}
+
+fn duration_stats(mut durations: Vec<i64>, head: Span) -> (Value, Value) {
+    if durations.is_empty() {
+        return (Value::nothing(head), Value::nothing(head));
+    }
+    durations.sort_unstable();
+    let sum: i128 = durations.iter().map(|d| *d as i128).sum();
+    let avg = (sum / durations.len() as i128) as i64;
+    let mid = durations.len() / 2;
+    let median = if durations.len() % 2 == 0 {
+        ((durations[mid - 1] as i128 + durations[mid] as i128) / 2) as i64
+    } else {
+        durations[mid]
+    };
+    (Value::duration(avg, head), Value::duration(median, head))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn item(command: &str, duration: Option<u64>) -> HistoryItem {
+        let mut item = HistoryItem::from_command_line(command);
+        item.duration = duration.map(Duration::from_nanos);
+        item
+    }
+
+    #[test]
+    fn duration_stats_empty_is_nothing() {
+        let (avg, median) = duration_stats(vec![], Span::test_data());
+        assert!(matches!(avg, Value::Nothing { .. }));
+        assert!(matches!(median, Value::Nothing { .. }));
+    }
+
+    #[test]
+    fn duration_stats_median_odd_count() {
+        let (avg, median) = duration_stats(vec![30, 10, 20], Span::test_data());
+        assert_eq!(avg.as_duration().unwrap(), 20);
+        assert_eq!(median.as_duration().unwrap(), 20);
+    }
+
+    #[test]
+    fn duration_stats_median_even_count() {
+        let (avg, median) = duration_stats(vec![10, 20, 30, 40], Span::test_data());
+        assert_eq!(avg.as_duration().unwrap(), 25);
+        // Average of the two middle values (20 and 30).
+        assert_eq!(median.as_duration().unwrap(), 25);
+    }
+
+    #[test]
+    fn compute_stats_counts_and_top_n_tie_break() {
+        let entries = vec![
+            item("b", None),
+            item("b", None),
+            item("a", None),
+            item("a", None),
+            item("c", None),
+        ];
+        let stats = compute_stats(&entries, 2, Span::test_data());
+        let record = stats.as_record().unwrap();
+
+        assert_eq!(record.get("total").unwrap().as_int().unwrap(), 5);
+        assert_eq!(record.get("unique").unwrap().as_int().unwrap(), 3);
+
+        let top = record.get("top_commands").unwrap().as_list().unwrap();
+        assert_eq!(top.len(), 2);
+        // `a` and `b` both occur twice; the tie breaks alphabetically so `a` wins.
+        let first = top[0].as_record().unwrap();
+        assert_eq!(first.get("command").unwrap().as_str().unwrap(), "a");
+        assert_eq!(first.get("count").unwrap().as_int().unwrap(), 2);
+        let second = top[1].as_record().unwrap();
+        assert_eq!(second.get("command").unwrap().as_str().unwrap(), "b");
+    }
+
+    #[test]
+    fn compute_stats_empty_has_no_busiest_hour() {
+        let stats = compute_stats(&[], 10, Span::test_data());
+        let record = stats.as_record().unwrap();
+        assert_eq!(record.get("total").unwrap().as_int().unwrap(), 0);
+        assert!(matches!(
+            record.get("busiest_hour").unwrap(),
+            Value::Nothing { .. }
+        ));
+        assert!(matches!(
+            record.get("avg_duration").unwrap(),
+            Value::Nothing { .. }
+        ));
+    }
+}
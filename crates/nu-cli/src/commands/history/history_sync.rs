@@ -0,0 +1,589 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chrono::{DateTime, Utc};
+use crypto_secretbox::{
+    aead::{Aead, OsRng},
+    AeadCore, KeyInit, XSalsa20Poly1305,
+};
+use nu_engine::command_prelude::*;
+use reedline::{HistoryItem, SearchDirection, SearchQuery};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::store::{open_store, HistoryStore};
+
+/// Nonce length for XSalsa20-Poly1305 (NaCl `secretbox`).
+const NONCE_LEN: usize = 24;
+
+#[derive(Clone)]
+pub struct HistorySync;
+
+impl Command for HistorySync {
+    fn name(&self) -> &str {
+        "history sync"
+    }
+
+    fn usage(&self) -> &str {
+        "Synchronize the local history with an end-to-end encrypted remote."
+    }
+
+    fn extra_usage(&self) -> &str {
+        "Each command is msgpack-encoded and encrypted client-side with \
+XSalsa20-Poly1305 before it leaves the machine, so the remote only ever stores \
+ciphertext. A symmetric key is generated on first use and kept in the config \
+directory; the sync URL and bearer token are read from `history-sync.json` in \
+that same directory."
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        Signature::build("history sync")
+            .input_output_types(vec![(Type::Nothing, Type::Record(vec![].into()))])
+            .switch(
+                "dry-run",
+                "Report what would be uploaded without contacting the remote",
+                None,
+            )
+            .category(Category::History)
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let head = call.head;
+        let dry_run = call.has_flag(engine_state, stack, "dry-run")?;
+
+        let Some(history) = engine_state.history_config() else {
+            return Ok(PipelineData::empty());
+        };
+
+        let config_path = nu_path::config_dir().ok_or(ShellError::ConfigDirNotFound {
+            span: Some(head),
+        })?;
+        let nushell_dir = {
+            let mut dir = config_path.clone();
+            dir.push("nushell");
+            dir
+        };
+
+        let settings = SyncSettings::load(&nushell_dir, head)?;
+
+        let cipher = Crypto::load_or_create(&settings.key_path, head)?;
+        let mut state = SyncState::load(&nushell_dir);
+
+        let store = open_store(&history, config_path, head)?;
+        let entries = store.search(SearchQuery::everything(SearchDirection::Forward, None))?;
+
+        // (a) Upload entries recorded after the last sync point. Entries without
+        // a timestamp (e.g. PlainText rows) can't be ordered, so we fall back to
+        // the per-record id we've already uploaded to avoid re-sending them.
+        let outgoing: Vec<HistoryItem> = entries
+            .iter()
+            .filter(|e| should_upload(e, &state))
+            .cloned()
+            .collect();
+
+        if dry_run {
+            return Ok(Value::record(
+                record! {
+                    "server" => Value::string(settings.url.clone(), head),
+                    "local_count" => Value::int(entries.len() as i64, head),
+                    "to_upload" => Value::int(outgoing.len() as i64, head),
+                    "last_sync" => last_sync_value(state.last_sync, head),
+                },
+                head,
+            )
+            .into_pipeline_data());
+        }
+
+        let client = SyncClient::new(settings);
+        let mut uploaded = 0usize;
+        for item in &outgoing {
+            let record = RemoteRecord::encrypt(&cipher, item)?;
+            client.upload(&record, head)?;
+            state.uploaded_ids.insert(record.id);
+            uploaded += 1;
+        }
+
+        // (b) Download remote records newer than the last sync and insert any we
+        // do not already have, keyed by the stable content id.
+        let known: HashSet<String> = entries.iter().map(record_id).collect();
+        let remote = client.download(state.last_sync, head)?;
+        let mut downloaded = 0usize;
+        for record in remote {
+            let item = record.decrypt(&cipher, head)?;
+            if known.contains(&record_id(&item)) {
+                continue;
+            }
+            store.save(item)?;
+            downloaded += 1;
+        }
+        store.sync()?;
+
+        state.last_sync = Some(Utc::now());
+        state.local_count = entries.len() + downloaded;
+        state.remote_count = client.count(head)?;
+        state.save(&nushell_dir, head)?;
+
+        Ok(Value::record(
+            record! {
+                "uploaded" => Value::int(uploaded as i64, head),
+                "downloaded" => Value::int(downloaded as i64, head),
+                "local_count" => Value::int(state.local_count as i64, head),
+                "remote_count" => Value::int(state.remote_count as i64, head),
+            },
+            head,
+        )
+        .into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                example: "history sync",
+                description: "Exchange history with the configured remote",
+                result: None,
+            },
+            Example {
+                example: "history sync --dry-run",
+                description: "Show what a sync would upload without sending anything",
+                result: None,
+            },
+        ]
+    }
+}
+
+/// Connection details for the remote, read from `history-sync.json` in the
+/// config directory so every piece of sync configuration (this file, the
+/// symmetric key and the sync state) lives together there. The file holds the
+/// remote endpoint (`url`), an optional bearer `token`, and an optional
+/// `key_path` for the symmetric key (defaulting to `history-sync.key` beside
+/// it).
+#[derive(Deserialize)]
+struct SyncConfig {
+    url: String,
+    #[serde(default)]
+    token: Option<String>,
+    #[serde(default)]
+    key_path: Option<String>,
+}
+
+struct SyncSettings {
+    url: String,
+    token: Option<String>,
+    key_path: PathBuf,
+}
+
+impl SyncSettings {
+    fn config_path(dir: &std::path::Path) -> PathBuf {
+        dir.join("history-sync.json")
+    }
+
+    fn load(nushell_dir: &std::path::Path, head: Span) -> Result<Self, ShellError> {
+        let path = Self::config_path(nushell_dir);
+        let raw = std::fs::read_to_string(&path).map_err(|_| ShellError::GenericError {
+            error: "history sync is not configured".into(),
+            msg: format!("create {} with the remote endpoint", path.display()),
+            span: Some(head),
+            help: Some(r#"e.g. {"url": "https://sync.example", "token": "…"}"#.into()),
+            inner: vec![],
+        })?;
+        let config: SyncConfig =
+            serde_json::from_str(&raw).map_err(|err| ShellError::GenericError {
+                error: "invalid history sync config".into(),
+                msg: err.to_string(),
+                span: Some(head),
+                help: None,
+                inner: vec![],
+            })?;
+        if config.url.trim().is_empty() {
+            return Err(ShellError::GenericError {
+                error: "history sync is not configured".into(),
+                msg: "`url` must name the remote endpoint".into(),
+                span: Some(head),
+                help: None,
+                inner: vec![],
+            });
+        }
+        let key_path = match config.key_path {
+            Some(path) if !path.trim().is_empty() => PathBuf::from(path),
+            _ => nushell_dir.join("history-sync.key"),
+        };
+        Ok(SyncSettings {
+            url: config.url,
+            token: config.token.filter(|token| !token.is_empty()),
+            key_path,
+        })
+    }
+}
+
+/// Persisted high-water mark for the sync loop.
+#[derive(Default, Serialize, Deserialize)]
+struct SyncState {
+    last_sync: Option<DateTime<Utc>>,
+    local_count: usize,
+    remote_count: usize,
+    /// Content ids already uploaded, used to avoid re-sending timestamp-less
+    /// entries that can't be ordered against `last_sync`.
+    #[serde(default)]
+    uploaded_ids: HashSet<String>,
+}
+
+impl SyncState {
+    fn path(dir: &std::path::Path) -> PathBuf {
+        dir.join("history-sync-state.json")
+    }
+
+    fn load(dir: &std::path::Path) -> Self {
+        std::fs::read_to_string(Self::path(dir))
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, dir: &std::path::Path, head: Span) -> Result<(), ShellError> {
+        let raw = serde_json::to_string_pretty(self).map_err(|err| ShellError::GenericError {
+            error: "failed to serialize sync state".into(),
+            msg: err.to_string(),
+            span: Some(head),
+            help: None,
+            inner: vec![],
+        })?;
+        std::fs::write(Self::path(dir), raw).map_err(|err| ShellError::IOError {
+            msg: err.to_string(),
+        })
+    }
+}
+
+/// Wraps the symmetric key used for the NaCl secretbox.
+struct Crypto {
+    cipher: XSalsa20Poly1305,
+}
+
+impl Crypto {
+    /// Load the base64 key from `path`, generating and persisting a fresh one on
+    /// first use.
+    fn load_or_create(path: &PathBuf, head: Span) -> Result<Self, ShellError> {
+        let key = match std::fs::read_to_string(path) {
+            Ok(raw) => BASE64
+                .decode(raw.trim())
+                .map_err(|err| ShellError::GenericError {
+                    error: "invalid history sync key".into(),
+                    msg: err.to_string(),
+                    span: Some(head),
+                    help: None,
+                    inner: vec![],
+                })?,
+            Err(_) => {
+                let key = XSalsa20Poly1305::generate_key(&mut OsRng);
+                if let Some(parent) = path.parent() {
+                    let _ = std::fs::create_dir_all(parent);
+                    restrict_permissions(parent, 0o700);
+                }
+                std::fs::write(path, BASE64.encode(key)).map_err(|err| ShellError::IOError {
+                    msg: err.to_string(),
+                })?;
+                // The whole point of client-side encryption is that this key
+                // never leaves the machine, so keep it owner-only.
+                restrict_permissions(path, 0o600);
+                key.to_vec()
+            }
+        };
+        let cipher = XSalsa20Poly1305::new_from_slice(&key).map_err(|_| {
+            ShellError::GenericError {
+                error: "invalid history sync key length".into(),
+                msg: "expected a 32-byte key".into(),
+                span: Some(head),
+                help: None,
+                inner: vec![],
+            }
+        })?;
+        Ok(Crypto { cipher })
+    }
+
+    /// Encrypt `plaintext`, returning the 24-byte nonce prepended to the ciphertext.
+    fn seal(&self, plaintext: &[u8], head: Span) -> Result<Vec<u8>, ShellError> {
+        let nonce = XSalsa20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| encryption_error("encrypt", head))?;
+        let mut out = nonce.to_vec();
+        out.extend_from_slice(&ciphertext);
+        Ok(out)
+    }
+
+    fn open(&self, sealed: &[u8], head: Span) -> Result<Vec<u8>, ShellError> {
+        if sealed.len() < NONCE_LEN {
+            return Err(encryption_error("decrypt", head));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(nonce.into(), ciphertext)
+            .map_err(|_| encryption_error("decrypt", head))
+    }
+}
+
+/// Tighten filesystem permissions on the key and its directory. A no-op on
+/// platforms without Unix mode bits.
+#[cfg(unix)]
+fn restrict_permissions(path: &std::path::Path, mode: u32) {
+    use std::os::unix::fs::PermissionsExt;
+    let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode));
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &std::path::Path, _mode: u32) {}
+
+fn encryption_error(op: &str, head: Span) -> ShellError {
+    ShellError::GenericError {
+        error: format!("failed to {op} history record"),
+        msg: "check that the sync key matches the remote".into(),
+        span: Some(head),
+        help: None,
+        inner: vec![],
+    }
+}
+
+/// Serializable mirror of the fields of a [`HistoryItem`] we sync.
+#[derive(Serialize, Deserialize)]
+struct ItemPayload {
+    command_line: String,
+    start_timestamp: Option<DateTime<Utc>>,
+    duration: Option<Duration>,
+    exit_status: Option<i64>,
+    cwd: Option<String>,
+    hostname: Option<String>,
+}
+
+impl ItemPayload {
+    fn from_item(item: &HistoryItem) -> Self {
+        ItemPayload {
+            command_line: item.command_line.clone(),
+            start_timestamp: item.start_timestamp,
+            duration: item.duration,
+            exit_status: item.exit_status,
+            cwd: item.cwd.clone(),
+            hostname: item.hostname.clone(),
+        }
+    }
+
+    fn into_item(self) -> HistoryItem {
+        let mut item = HistoryItem::from_command_line(self.command_line);
+        item.start_timestamp = self.start_timestamp;
+        item.duration = self.duration;
+        item.exit_status = self.exit_status;
+        item.cwd = self.cwd;
+        item.hostname = self.hostname;
+        item
+    }
+}
+
+/// An opaque record as stored on the remote: a content id plus ciphertext.
+#[derive(Serialize, Deserialize)]
+struct RemoteRecord {
+    id: String,
+    #[serde(with = "base64_bytes")]
+    data: Vec<u8>,
+}
+
+impl RemoteRecord {
+    fn encrypt(crypto: &Crypto, item: &HistoryItem) -> Result<Self, ShellError> {
+        let head = Span::unknown();
+        let payload = ItemPayload::from_item(item);
+        let packed = rmp_serde::to_vec(&payload).map_err(|err| ShellError::GenericError {
+            error: "failed to encode history record".into(),
+            msg: err.to_string(),
+            span: None,
+            help: None,
+            inner: vec![],
+        })?;
+        Ok(RemoteRecord {
+            id: record_id(item),
+            data: crypto.seal(&packed, head)?,
+        })
+    }
+
+    fn decrypt(&self, crypto: &Crypto, head: Span) -> Result<HistoryItem, ShellError> {
+        let packed = crypto.open(&self.data, head)?;
+        let payload: ItemPayload =
+            rmp_serde::from_slice(&packed).map_err(|err| ShellError::GenericError {
+                error: "failed to decode history record".into(),
+                msg: err.to_string(),
+                span: Some(head),
+                help: None,
+                inner: vec![],
+            })?;
+        Ok(payload.into_item())
+    }
+}
+
+/// A stable, idempotent id derived from the content of an entry.
+fn record_id(item: &HistoryItem) -> String {
+    let mut hasher = Sha256::new();
+    if let Some(ts) = item.start_timestamp {
+        hasher.update(ts.timestamp_nanos_opt().unwrap_or_default().to_le_bytes());
+    }
+    hasher.update(item.command_line.as_bytes());
+    hasher.update(item.cwd.as_deref().unwrap_or_default().as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Decide whether an entry still needs uploading. Timestamped entries are
+/// compared against the last sync point; entries without a timestamp are
+/// uploaded only if their content id hasn't been sent before.
+fn should_upload(item: &HistoryItem, state: &SyncState) -> bool {
+    match item.start_timestamp {
+        Some(ts) => state.last_sync.map_or(true, |last| ts > last),
+        None => !state.uploaded_ids.contains(&record_id(item)),
+    }
+}
+
+fn last_sync_value(last_sync: Option<DateTime<Utc>>, head: Span) -> Value {
+    match last_sync {
+        Some(ts) => Value::string(ts.to_rfc3339(), head),
+        None => Value::nothing(head),
+    }
+}
+
+/// Minimal HTTP client for the remote sync protocol.
+struct SyncClient {
+    settings: SyncSettings,
+    agent: ureq::Agent,
+}
+
+impl SyncClient {
+    fn new(settings: SyncSettings) -> Self {
+        SyncClient {
+            settings,
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let url = format!("{}/{path}", self.settings.url.trim_end_matches('/'));
+        let req = self.agent.request(method, &url);
+        match &self.settings.token {
+            Some(token) => req.set("Authorization", &format!("Bearer {token}")),
+            None => req,
+        }
+    }
+
+    fn upload(&self, record: &RemoteRecord, head: Span) -> Result<(), ShellError> {
+        self.request("POST", "records")
+            .send_json(serde_json::to_value(record).unwrap_or_default())
+            .map_err(|err| network_error(err, head))?;
+        Ok(())
+    }
+
+    fn download(
+        &self,
+        after: Option<DateTime<Utc>>,
+        head: Span,
+    ) -> Result<Vec<RemoteRecord>, ShellError> {
+        let mut req = self.request("GET", "records");
+        if let Some(after) = after {
+            req = req.query("after", &after.to_rfc3339());
+        }
+        let resp = req.call().map_err(|err| network_error(err, head))?;
+        resp.into_json().map_err(|err| ShellError::IOError {
+            msg: format!("failed to decode remote records: {err}"),
+        })
+    }
+
+    fn count(&self, head: Span) -> Result<usize, ShellError> {
+        #[derive(Deserialize)]
+        struct Count {
+            count: usize,
+        }
+        let resp = self
+            .request("GET", "count")
+            .call()
+            .map_err(|err| network_error(err, head))?;
+        let count: Count = resp.into_json().map_err(|err| ShellError::IOError {
+            msg: format!("failed to decode remote count: {err}"),
+        })?;
+        Ok(count.count)
+    }
+}
+
+fn network_error(err: ureq::Error, head: Span) -> ShellError {
+    ShellError::NetworkFailure {
+        msg: format!("history sync request failed: {err}"),
+        span: head,
+    }
+}
+
+/// Serde helper that stores `Vec<u8>` as a base64 string on the wire.
+mod base64_bytes {
+    use super::BASE64;
+    use base64::Engine as _;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        BASE64.decode(raw.as_bytes()).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crypto() -> Crypto {
+        let key = XSalsa20Poly1305::generate_key(&mut OsRng);
+        Crypto {
+            cipher: XSalsa20Poly1305::new(&key),
+        }
+    }
+
+    #[test]
+    fn seal_open_roundtrips() {
+        let crypto = crypto();
+        let head = Span::test_data();
+        let plaintext = b"git commit -m 'hello'";
+        let sealed = crypto.seal(plaintext, head).unwrap();
+        // Ciphertext is the 24-byte nonce followed by the encrypted payload.
+        assert!(sealed.len() > NONCE_LEN);
+        assert_ne!(&sealed[NONCE_LEN..], plaintext);
+        assert_eq!(crypto.open(&sealed, head).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn open_rejects_short_and_tampered_input() {
+        let crypto = crypto();
+        let head = Span::test_data();
+        assert!(crypto.open(&[0u8; 4], head).is_err());
+        let mut sealed = crypto.seal(b"ls", head).unwrap();
+        *sealed.last_mut().unwrap() ^= 0xff;
+        assert!(crypto.open(&sealed, head).is_err());
+    }
+
+    #[test]
+    fn record_id_is_stable_and_content_addressed() {
+        let mut a = HistoryItem::from_command_line("ls");
+        a.start_timestamp = Some(Utc::now());
+        a.cwd = Some("/tmp".into());
+
+        let mut same = HistoryItem::from_command_line("ls");
+        same.start_timestamp = a.start_timestamp;
+        same.cwd = Some("/tmp".into());
+
+        let mut other = a.clone();
+        other.command_line = "ls -l".into();
+
+        assert_eq!(record_id(&a), record_id(&same));
+        assert_ne!(record_id(&a), record_id(&other));
+    }
+}
@@ -0,0 +1,10 @@
+mod history_;
+mod history_import;
+mod history_stats;
+mod history_sync;
+mod store;
+
+pub use history_::History;
+pub use history_import::HistoryImport;
+pub use history_stats::HistoryStats;
+pub use history_sync::HistorySync;
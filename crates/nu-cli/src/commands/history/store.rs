@@ -0,0 +1,218 @@
+use nu_engine::command_prelude::*;
+use nu_protocol::HistoryFileFormat;
+use nu_protocol::{HISTORY_DEST_SQLITE, HISTORY_DEST_TXT};
+use reedline::{
+    FileBackedHistory, History as ReedlineHistory, HistoryItem, RqliteBackedHistory, SearchQuery,
+    SqliteBackedHistory, HistoryStorageDest,
+};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// One interface over the three history backends (PlainText/Sqlite/Rqlite) so
+/// callers do a single "search and map to records" instead of branching over
+/// `HistoryFileFormat`. For the local SQLite backend the underlying reedline
+/// handle is pooled and reused across invocations; the others are opened per
+/// call (see [`open_store`]).
+pub(super) trait HistoryStore: Send {
+    fn search(&self, query: SearchQuery) -> Result<Vec<HistoryItem>, ShellError>;
+    fn save(&self, item: HistoryItem) -> Result<(), ShellError>;
+    fn sync(&self) -> Result<(), ShellError>;
+    fn clear(&self) -> Result<(), ShellError>;
+}
+
+type SharedHistory = Arc<Mutex<Box<dyn ReedlineHistory>>>;
+
+/// A reedline backend behind the [`HistoryStore`] interface. Cloning is cheap —
+/// every clone shares the same connection, which is what lets `history`,
+/// `history stats` and the filtering path reuse a single pooled handle.
+///
+/// `pool_key` is set only for entries that actually live in the shared pool
+/// (local SQLite). When an operation on a pooled handle fails, the entry is
+/// evicted so the next call rebuilds it instead of reusing a dead connection.
+#[derive(Clone)]
+pub(super) struct PooledStore {
+    inner: SharedHistory,
+    dest: HistoryStorageDest,
+    network: bool,
+    pool_key: Option<String>,
+}
+
+impl PooledStore {
+    fn map_err(&self, err: reedline::ReedlineError) -> ShellError {
+        // A failed operation may mean the cached handle is no longer usable;
+        // drop it so a later call reconnects rather than reusing a dead one.
+        if let Some(key) = &self.pool_key {
+            pool().lock().expect("history pool poisoned").remove(key);
+        }
+        if self.network {
+            ShellError::NetworkFailure {
+                msg: format!("rqlite error: {}\n{err:?}", self.dest),
+                span: Span::unknown(),
+            }
+        } else {
+            ShellError::IOError {
+                msg: format!("{}, {err:?}", self.dest),
+            }
+        }
+    }
+}
+
+impl HistoryStore for PooledStore {
+    fn search(&self, query: SearchQuery) -> Result<Vec<HistoryItem>, ShellError> {
+        let guard = self.inner.lock().expect("history pool poisoned");
+        guard.search(query).map_err(|err| self.map_err(err))
+    }
+
+    fn save(&self, item: HistoryItem) -> Result<(), ShellError> {
+        let mut guard = self.inner.lock().expect("history pool poisoned");
+        guard.save(item).map(|_| ()).map_err(|err| self.map_err(err))
+    }
+
+    fn sync(&self) -> Result<(), ShellError> {
+        let mut guard = self.inner.lock().expect("history pool poisoned");
+        guard.sync().map_err(|err| self.map_err(err))
+    }
+
+    fn clear(&self) -> Result<(), ShellError> {
+        let mut guard = self.inner.lock().expect("history pool poisoned");
+        guard.clear().map_err(|err| self.map_err(err))
+    }
+}
+
+/// Process-wide pool of open SQLite history handles, keyed by database path.
+///
+/// This plays the role Atuin's `SqlitePool` does: one lazily-initialized,
+/// shared connection reused across calls rather than a fresh
+/// `SqliteBackedHistory::with_file(...)` per invocation. Only the local SQLite
+/// file is pooled — its handle is cheap to keep and keyed by an absolute path,
+/// so it can't leak between unrelated databases. PlainText and Rqlite are
+/// rebuilt per call (see [`open_store`]).
+fn pool() -> &'static Mutex<HashMap<String, SharedHistory>> {
+    static POOL: OnceLock<Mutex<HashMap<String, SharedHistory>>> = OnceLock::new();
+    POOL.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Open (or reuse) the store for the configured backend.
+pub(super) fn open_store(
+    history: &nu_protocol::HistoryConfig,
+    config_path: PathBuf,
+    head: Span,
+) -> Result<Box<dyn HistoryStore>, ShellError> {
+    let (dest, network) = destination(history, config_path);
+
+    // Only the local SQLite backend is pooled. FileBackedHistory loads the
+    // whole file into memory once, so a cached handle would never see commands
+    // the REPL appends later. Rqlite must be reconnected per call (matching the
+    // baseline): a pooled network handle that goes bad — the remote drops
+    // mid-session — would otherwise stay cached and fail every later call.
+    if !matches!(history.file_format, HistoryFileFormat::Sqlite) {
+        let backend = build_backend(history, &dest, network, head)?;
+        return Ok(Box::new(PooledStore {
+            inner: Arc::new(Mutex::new(backend)),
+            dest,
+            network,
+            pool_key: None,
+        }));
+    }
+
+    let key = dest.to_string();
+    let mut pool = pool().lock().expect("history pool poisoned");
+    if let Some(inner) = pool.get(&key) {
+        return Ok(Box::new(PooledStore {
+            inner: inner.clone(),
+            dest,
+            network,
+            pool_key: Some(key),
+        }));
+    }
+
+    let backend = build_backend(history, &dest, network, head)?;
+    let inner: SharedHistory = Arc::new(Mutex::new(backend));
+    pool.insert(key.clone(), inner.clone());
+    Ok(Box::new(PooledStore {
+        inner,
+        dest,
+        network,
+        pool_key: Some(key),
+    }))
+}
+
+fn destination(
+    history: &nu_protocol::HistoryConfig,
+    config_path: PathBuf,
+) -> (HistoryStorageDest, bool) {
+    match history.file_format {
+        HistoryFileFormat::Sqlite | HistoryFileFormat::PlainText => {
+            let mut history_path = config_path;
+            history_path.push("nushell");
+            if matches!(history.file_format, HistoryFileFormat::Sqlite) {
+                history_path.push(HISTORY_DEST_SQLITE);
+            } else {
+                history_path.push(HISTORY_DEST_TXT);
+            }
+            (HistoryStorageDest::Path(history_path), false)
+        }
+        HistoryFileFormat::Rqlite => (history.rqlite_url.clone().into(), true),
+    }
+}
+
+fn build_backend(
+    history: &nu_protocol::HistoryConfig,
+    dest: &HistoryStorageDest,
+    network: bool,
+    head: Span,
+) -> Result<Box<dyn ReedlineHistory>, ShellError> {
+    match history.file_format {
+        // Enable WAL journal mode (persisted on the database file) so concurrent
+        // readers (e.g. the prompt) don't block on the pooled writer.
+        HistoryFileFormat::Sqlite => {
+            if let HistoryStorageDest::Path(path) = dest {
+                enable_wal(path)?;
+            }
+            SqliteBackedHistory::with_file(dest.clone(), None, None)
+                .map(|inner| Box::new(inner) as Box<dyn ReedlineHistory>)
+                .map_err(|err| ShellError::IOError {
+                    msg: format!("{dest}, {err:?}"),
+                })
+        }
+        HistoryFileFormat::PlainText => {
+            FileBackedHistory::with_file(history.max_size as usize, dest.clone())
+                .map(|inner| Box::new(inner) as Box<dyn ReedlineHistory>)
+                .map_err(|err| ShellError::IOError {
+                    msg: format!("{dest}, {err:?}"),
+                })
+        }
+        HistoryFileFormat::Rqlite => {
+            let _ = network;
+            RqliteBackedHistory::with_url(dest.clone(), None, None)
+                .map(|inner| Box::new(inner) as Box<dyn ReedlineHistory>)
+                .map_err(|err| ShellError::NetworkFailure {
+                    msg: format!("Failed to connect rqlite: {dest}\n{err:?}"),
+                    span: head,
+                })
+        }
+    }
+}
+
+/// Switch the SQLite history database to WAL journal mode. WAL is persisted in
+/// the database header, so once it is set every later connection already opens
+/// in WAL — we read the current mode first and only take the extra write when it
+/// isn't WAL yet, so the cost is paid once rather than on every cold open.
+fn enable_wal(path: &std::path::Path) -> Result<(), ShellError> {
+    let conn = rusqlite::Connection::open(path).map_err(|err| ShellError::IOError {
+        msg: format!("{}, {err}", path.display()),
+    })?;
+    let current: String = conn
+        .pragma_query_value(None, "journal_mode", |row| row.get(0))
+        .map_err(|err| ShellError::IOError {
+            msg: format!("{}, failed to read journal mode: {err}", path.display()),
+        })?;
+    if current.eq_ignore_ascii_case("wal") {
+        return Ok(());
+    }
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|err| ShellError::IOError {
+            msg: format!("{}, failed to enable WAL: {err}", path.display()),
+        })
+}